@@ -1,34 +1,78 @@
 #![no_std]
 
+extern crate alloc;
+
 #[cfg(test)]
 extern crate std;
 
+mod madt;
+mod mcfg;
 mod rsdp;
 mod sdt;
 
+use alloc::vec::Vec;
+use core::mem;
 use core::ops::Deref;
 use core::ptr::NonNull;
+use madt::InterruptModel;
+use mcfg::PciConfigRegion;
 use rsdp::Rsdp;
+use sdt::SdtHeader;
+
+pub use rsdp::search_for_rsdp;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum AcpiError
 {
     RsdpIncorrectSignature,
     RsdpInvalidOemId,
     RsdpInvalidChecksum,
+    RsdpInvalidExtendedChecksum,
+    RsdpNotFound,
+    SdtIncorrectSignature,
+    SdtInvalidLength,
+    SdtInvalidChecksum,
+    MadtInvalidLength,
+    MadtInvalidEntry,
+}
+
+/// The result of successfully parsing a set of ACPI tables. Individual fields are populated as
+/// the corresponding tables are found and understood; a table this crate doesn't yet recognise
+/// is simply skipped.
+#[derive(Debug, Default)]
+pub struct Acpi
+{
+    pub interrupt_model       : Option<InterruptModel>,
+    pub pci_config_regions    : Vec<PciConfigRegion>,
 }
 
-/// Describes a physical mapping created by `AcpiHandler::map_physical_region` and unmapped by
-/// `AcpiHandler::unmap_physical_region`. The region mapped must be at least `mem::size_of::<T>()`
-/// bytes, but may be bigger.
-pub struct PhysicalMapping<T>
+impl Acpi
+{
+    /// Compute the ECAM physical address of the configuration space for `(segment, bus, device,
+    /// function)`, if the MCFG described a region covering that segment group and bus.
+    pub fn pci_config_address(&self, segment : u16, bus : u8, device : u8, function : u8) -> Option<u64>
+    {
+        self.pci_config_regions
+            .iter()
+            .find(|region| region.segment_group == segment && (region.bus_start..=region.bus_end).contains(&bus))
+            .map(|region| region.address_for(bus, device, function))
+    }
+}
+
+/// Describes a physical mapping created by `AcpiHandler::map_physical_region`. Holds a clone of
+/// the handler that created it, so it can unmap itself on `Drop` instead of requiring the caller
+/// to remember to do so.
+pub struct PhysicalMapping<H, T>
+    where H : AcpiHandler
 {
     pub physical_start  : usize,
     pub virtual_start   : NonNull<T>,
     pub mapped_length   : usize,    // Differs from `region_length` if padding is added to align to page boundaries
+    handler             : H,
 }
 
-impl<T> Deref for PhysicalMapping<T>
+impl<H, T> Deref for PhysicalMapping<H, T>
+    where H : AcpiHandler
 {
     type Target = T;
 
@@ -41,34 +85,114 @@ impl<T> Deref for PhysicalMapping<T>
     }
 }
 
+impl<H, T> Drop for PhysicalMapping<H, T>
+    where H : AcpiHandler
+{
+    fn drop(&mut self)
+    {
+        self.handler.unmap_physical_region(self);
+    }
+}
+
 /// The kernel must provide an implementation of this trait for `acpi` to interface with. It has
 /// utility methods `acpi` uses to for e.g. mapping physical memory, but also an interface for
 /// `acpi` to tell the kernel about the tables it's parsing, such as how the kernel should
 /// configure the APIC or PCI routing.
-pub trait AcpiHandler
+///
+/// Implementors must be `Clone`, as a clone of the handler is stored in every `PhysicalMapping` so
+/// it can unmap itself when dropped.
+pub trait AcpiHandler : Clone
 {
-    /// Given a starting physical address, map a region of physical memory that contains a `T`
-    /// somewhere in the virtual address space. The address doesn't have to be page-aligned, so
-    /// the implementation may have to add padding to either end.
-    fn map_physical_region<T>(&mut self, physical_address : usize) -> PhysicalMapping<T>;
-
-    /// Unmap the given physical mapping. Safe because we consume the mapping, and so it can't be
-    /// used after being passed to this function.
-    fn unmap_physical_region<T>(&mut self, region : PhysicalMapping<T>);
+    /// Given a starting physical address, map `size` bytes of physical memory somewhere in the
+    /// virtual address space, and interpret it as a `T`. The address doesn't have to be
+    /// page-aligned, so the implementation may have to add padding to either end. `size` may be
+    /// bigger than `mem::size_of::<T>()`, for tables whose length isn't known until runtime.
+    fn map_physical_region<T>(&self, physical_address : usize, size : usize) -> PhysicalMapping<Self, T>;
+
+    /// Unmap the given physical mapping. Called automatically by `PhysicalMapping::drop`; there
+    /// should be no need to call this directly.
+    fn unmap_physical_region<T>(&self, region : &PhysicalMapping<Self, T>);
 }
 
 /// This is the entry point of `acpi`. Given the **physical** address of the RSDP, it parses all
 /// the SDTs in the RSDT, calling the relevant handlers in the implementation's `AcpiHandler`.
-pub fn parse_acpi<T>(handler : &mut T, rsdp_address : usize) -> Result<(), AcpiError>
+pub fn parse_acpi<T>(handler : &T, rsdp_address : usize) -> Result<Acpi, AcpiError>
     where T : AcpiHandler
 {
-    let rsdp_mapping = handler.map_physical_region::<Rsdp>(rsdp_address);
+    let rsdp_mapping = handler.map_physical_region::<Rsdp>(rsdp_address, mem::size_of::<Rsdp>());
     (*rsdp_mapping).validate()?;
 
-    // TODO: map the RSDT
-    // TODO: parse the RSDT
+    let use_xsdt = rsdp_mapping.revision() >= 2;
+    let (expected_signature, root_table_address) : (&[u8; 4], u64) = if use_xsdt
+    {
+        (b"XSDT", rsdp_mapping.xsdt_address())
+    }
+    else
+    {
+        (b"RSDT", rsdp_mapping.rsdt_address() as u64)
+    };
+
+    let root_table_mapping = map_whole_table(handler, root_table_address as usize)?;
+    root_table_mapping.validate(expected_signature)?;
+
+    let mut acpi = Acpi::default();
+
+    if use_xsdt
+    {
+        for i in 0..root_table_mapping.num_entries_u64()
+        {
+            handle_child_table(handler, &mut acpi, unsafe { root_table_mapping.entry_u64(i) })?;
+        }
+    }
+    else
+    {
+        for i in 0..root_table_mapping.num_entries_u32()
+        {
+            handle_child_table(handler, &mut acpi, unsafe { root_table_mapping.entry_u32(i) } as u64)?;
+        }
+    }
+
+    Ok(acpi)
+}
+
+/// Map an SDT at `physical_address`, first mapping just enough to read its header's `length`,
+/// then remapping to cover the whole table.
+fn map_whole_table<T>(handler : &T, physical_address : usize) -> Result<PhysicalMapping<T, SdtHeader>, AcpiError>
+    where T : AcpiHandler
+{
+    let length = handler.map_physical_region::<SdtHeader>(physical_address, mem::size_of::<SdtHeader>()).length();
+
+    if (length as usize) < mem::size_of::<SdtHeader>()
+    {
+        return Err(AcpiError::SdtInvalidLength);
+    }
+
+    Ok(handler.map_physical_region::<SdtHeader>(physical_address, length as usize))
+}
+
+/// Map, dispatch on, and unmap a single child SDT referenced from the RSDT/XSDT.
+fn handle_child_table<T>(handler : &T, acpi : &mut Acpi, physical_address : u64) -> Result<(), AcpiError>
+    where T : AcpiHandler
+{
+    let header_mapping = map_whole_table(handler, physical_address as usize)?;
+
+    match &header_mapping.signature()
+    {
+        b"APIC" =>
+        {
+            header_mapping.validate(b"APIC")?;
+            acpi.interrupt_model = Some(madt::parse_madt(&header_mapping)?);
+        },
+
+        b"MCFG" =>
+        {
+            header_mapping.validate(b"MCFG")?;
+            acpi.pci_config_regions = mcfg::parse_mcfg(&header_mapping);
+        },
+
+        _ => (),
+    }
 
-    handler.unmap_physical_region(rsdp_mapping);
     Ok(())
 }
 
@@ -81,79 +205,142 @@ mod constructed_table_tests
     use std::mem;
     use std::ptr::NonNull;
     use std::boxed::Box;
-    use {AcpiHandler, PhysicalMapping, parse_acpi, rsdp::Rsdp};
+    use {AcpiHandler, PhysicalMapping, parse_acpi, madt, mcfg, rsdp::Rsdp, sdt::SdtHeader};
 
     const OEM_ID : &[u8; 6] = b"RUST  ";
 
     /*
      * We use fake physical addresses to track what is being requested. When a particular table or
-     * resource is requested, we just allocate it on the heap and return the "virtual address"
-     * (a pointer onto the heap).
+     * resource is requested, we just build its canonical bytes on the heap and return the
+     * "virtual address" (a pointer onto the heap).
      */
     const RSDP_ADDRESS : usize = 0x0;
     const RSDT_ADDRESS : usize = 0x1;
+    const XSDT_ADDRESS : usize = 0x2;
+    const MADT_ADDRESS : usize = 0x3;
+    const MCFG_ADDRESS : usize = 0x4;
 
-    struct TestHandler { }
+    /// `revision` selects whether `TestHandler` hands out an ACPI 1.0 RSDP pointing at the RSDT,
+    /// or an ACPI 2.0+ RSDP pointing at the XSDT. `truncate_madt` makes the MADT's header report
+    /// a `length` shorter than `SdtHeader` itself, to exercise `map_whole_table`'s handling of a
+    /// corrupt/malicious length.
+    #[derive(Clone)]
+    struct TestHandler { revision : u8, truncate_madt : bool }
 
-    impl AcpiHandler for TestHandler
+    impl TestHandler
     {
-        fn map_physical_region<T>(&mut self, physical_address : usize) -> PhysicalMapping<T>
+        /// The full canonical bytes of the table at `physical_address`, as if it were mapped in
+        /// its entirety. `map_physical_region` copies however many of these bytes the caller
+        /// asked to map, so this also exercises mapping just a table's header.
+        fn canonical_bytes(&self, physical_address : usize) -> Box<[u8]>
         {
             match physical_address
             {
-                RSDP_ADDRESS =>
+                RSDP_ADDRESS => Rsdp::make_testcase(*b"RSD PTR ",
+                                                     None,
+                                                     *OEM_ID,
+                                                     self.revision,
+                                                     RSDT_ADDRESS as u32,
+                                                     mem::size_of::<Rsdp>() as u32,
+                                                     XSDT_ADDRESS as u64,
+                                                     None,
+                                                     [0, 0, 0]
+                                                    ),
+
+                RSDT_ADDRESS => SdtHeader::make_testcase(*b"RSDT", 1, *OEM_ID, *b"RUSTTABL", 0, 0, 0, &[MADT_ADDRESS as u32, MCFG_ADDRESS as u32]),
+
+                XSDT_ADDRESS => SdtHeader::make_testcase_u64(*b"XSDT", 1, *OEM_ID, *b"RUSTTABL", 0, 0, 0, &[MADT_ADDRESS as u64, MCFG_ADDRESS as u64]),
+
+                MADT_ADDRESS if self.truncate_madt =>
                 {
-                    let rsdp = Rsdp::make_testcase(*b"RSD PTR ",
-                                                   None,
-                                                   *OEM_ID,
-                                                   0,
-                                                   RSDT_ADDRESS as u32,
-                                                   0,
-                                                   0x0,
-                                                   None,
-                                                   [0, 0, 0]
-                                                  );
-
-                    PhysicalMapping
-                    {
-                        physical_start  : RSDP_ADDRESS,
-                        virtual_start   : unsafe
-                                          {
-                                              NonNull::<T>::new_unchecked(Box::into_raw(Box::new(rsdp)) as *mut T)
-                                          },
-                        mapped_length   : mem::size_of::<Rsdp>(),
-                    }
+                    let mut bytes = ::std::vec::Vec::new();
+                    bytes.extend_from_slice(b"APIC");
+                    bytes.extend_from_slice(&3u32.to_ne_bytes()); // length=3, shorter than SdtHeader itself
+                    bytes.into_boxed_slice()
                 },
 
+                MADT_ADDRESS => madt::make_testcase(*OEM_ID),
+
+                MCFG_ADDRESS => mcfg::make_testcase(*OEM_ID),
+
                 _ => panic!("ACPI requested invalid physical address: {:#x}", physical_address),
             }
         }
+    }
 
-        fn unmap_physical_region<T>(&mut self, region : PhysicalMapping<T>)
+    impl AcpiHandler for TestHandler
+    {
+        fn map_physical_region<T>(&self, physical_address : usize, size : usize) -> PhysicalMapping<Self, T>
         {
-            match region.physical_start
-            {
-                RSDP_ADDRESS =>
-                {
-                    let _ = unsafe { Box::from_raw(region.virtual_start.as_ptr()) };
-                },
+            let canonical = self.canonical_bytes(physical_address);
+            let copy_length = ::std::cmp::min(size, canonical.len());
+
+            let mut bytes = ::std::vec![0u8; size].into_boxed_slice();
+            bytes[..copy_length].copy_from_slice(&canonical[..copy_length]);
 
-                address => panic!("ACPI tried to unmap a region not created by test harness: {:#x}", address),
+            PhysicalMapping
+            {
+                physical_start  : physical_address,
+                virtual_start   : unsafe
+                                  {
+                                      NonNull::<T>::new_unchecked(Box::into_raw(bytes) as *mut u8 as *mut T)
+                                  },
+                mapped_length   : size,
+                handler         : self.clone(),
             }
         }
+
+        fn unmap_physical_region<T>(&self, region : &PhysicalMapping<Self, T>)
+        {
+            let slice_ptr = unsafe
+            {
+                ::std::slice::from_raw_parts_mut(region.virtual_start.as_ptr() as *mut u8, region.mapped_length)
+            };
+            let _ = unsafe { Box::from_raw(slice_ptr) };
+        }
     }
 
     #[test]
-    fn test_constructed_tables()
+    fn test_constructed_tables_acpi_1()
     {
-        let mut test_handler = TestHandler { };
-        match parse_acpi(&mut test_handler, RSDP_ADDRESS)
+        let test_handler = TestHandler { revision : 0, truncate_madt : false };
+        let acpi = match parse_acpi(&test_handler, RSDP_ADDRESS)
         {
-            Ok(_) => (),
+            Ok(acpi) => acpi,
             Err(err) =>
             {
                 panic!("Failed to parse ACPI: {:#?}", err);
             },
-        }
+        };
+
+        assert!(acpi.interrupt_model.is_some());
+        assert_eq!(acpi.pci_config_regions.len(), 1);
+        assert!(acpi.pci_config_address(0, 0, 0, 0).is_some());
+    }
+
+    #[test]
+    fn test_constructed_tables_rejects_child_table_shorter_than_header()
+    {
+        let test_handler = TestHandler { revision : 0, truncate_madt : true };
+        let result = parse_acpi(&test_handler, RSDP_ADDRESS);
+        assert_eq!(result.err(), Some(::AcpiError::SdtInvalidLength));
+    }
+
+    #[test]
+    fn test_constructed_tables_acpi_2()
+    {
+        let test_handler = TestHandler { revision : 2, truncate_madt : false };
+        let acpi = match parse_acpi(&test_handler, RSDP_ADDRESS)
+        {
+            Ok(acpi) => acpi,
+            Err(err) =>
+            {
+                panic!("Failed to parse ACPI: {:#?}", err);
+            },
+        };
+
+        assert!(acpi.interrupt_model.is_some());
+        assert_eq!(acpi.pci_config_regions.len(), 1);
+        assert!(acpi.pci_config_address(0, 0, 0, 0).is_some());
     }
 }
@@ -0,0 +1,241 @@
+use alloc::vec::Vec;
+use core::mem;
+use sdt::SdtHeader;
+use AcpiError;
+
+/// Offset, in bytes from the start of the MADT, of the first interrupt-controller-structure
+/// entry: the SDT header, followed by a 32-bit local APIC address and a 32-bit flags field.
+const FIRST_ENTRY_OFFSET : usize = mem::size_of::<SdtHeader>() + mem::size_of::<u32>() + mem::size_of::<u32>();
+
+#[derive(Debug, Clone, Copy)]
+pub struct ProcessorLocalApic
+{
+    pub processor_id   : u8,
+    pub apic_id        : u8,
+    pub enabled        : bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct IoApic
+{
+    pub id                              : u8,
+    pub address                         : u32,
+    pub global_system_interrupt_base    : u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct InterruptSourceOverride
+{
+    pub bus                        : u8,
+    pub irq                        : u8,
+    pub global_system_interrupt    : u32,
+    pub flags                      : u16,
+}
+
+/// Describes the interrupt-controller topology of the machine, as parsed from the MADT ("APIC"
+/// table). A kernel uses this to bring up its local/IO APICs and to remap legacy ISA IRQs that
+/// have been rerouted.
+#[derive(Debug, Default)]
+pub struct InterruptModel
+{
+    pub local_apic_address              : u64,
+    pub processors                      : Vec<ProcessorLocalApic>,
+    pub io_apics                        : Vec<IoApic>,
+    pub interrupt_source_overrides      : Vec<InterruptSourceOverride>,
+}
+
+/// The minimum `entry_length` needed to read the fields `parse_madt` reads for a given
+/// `entry_type`, including the entry's own 2-byte type/length header. Unrecognised types are only
+/// skipped over, so they just need their header to be present.
+fn min_entry_length(entry_type : u8) -> u8
+{
+    match entry_type
+    {
+        0 => 8,  // Processor Local APIC
+        1 => 12, // IO APIC
+        2 => 10, // Interrupt Source Override
+        5 => 12, // Local APIC Address Override
+        _ => 2,
+    }
+}
+
+/// Parse a MADT, given a mapping of its header that extends for the whole table.
+pub fn parse_madt(header : &SdtHeader) -> Result<InterruptModel, AcpiError>
+{
+    if (header.length() as usize) < FIRST_ENTRY_OFFSET
+    {
+        return Err(AcpiError::MadtInvalidLength);
+    }
+
+    let mut model = InterruptModel
+    {
+        local_apic_address: unsafe { header.read_unaligned::<u32>(mem::size_of::<SdtHeader>()) as u64 },
+        ..Default::default()
+    };
+
+    let mut offset = FIRST_ENTRY_OFFSET;
+
+    while offset + 2 <= header.length() as usize
+    {
+        let entry_type      : u8 = unsafe { header.read_unaligned(offset) };
+        let entry_length    : u8 = unsafe { header.read_unaligned(offset + 1) };
+
+        if entry_length == 0
+           || offset + entry_length as usize > header.length() as usize
+           || entry_length < min_entry_length(entry_type)
+        {
+            return Err(AcpiError::MadtInvalidEntry);
+        }
+
+        match entry_type
+        {
+            /*
+             * Processor Local APIC: processor id, APIC id, then a 32-bit flags field whose low
+             * bit says whether the processor is enabled.
+             */
+            0 =>
+            {
+                let processor_id    : u8 = unsafe { header.read_unaligned(offset + 2) };
+                let apic_id         : u8 = unsafe { header.read_unaligned(offset + 3) };
+                let flags           : u32 = unsafe { header.read_unaligned(offset + 4) };
+
+                model.processors.push(ProcessorLocalApic { processor_id, apic_id, enabled: flags & 0x1 != 0 });
+            },
+
+            /*
+             * IO APIC: id, a reserved byte, then the IO APIC's physical address and the first
+             * global system interrupt it's responsible for.
+             */
+            1 =>
+            {
+                let id                              : u8 = unsafe { header.read_unaligned(offset + 2) };
+                let address                          : u32 = unsafe { header.read_unaligned(offset + 4) };
+                let global_system_interrupt_base     : u32 = unsafe { header.read_unaligned(offset + 8) };
+
+                model.io_apics.push(IoApic { id, address, global_system_interrupt_base });
+            },
+
+            /*
+             * Interrupt Source Override: bus (always 0, ISA), source IRQ, the global system
+             * interrupt it's been remapped to, and the MPS INTI flags describing its polarity and
+             * trigger mode.
+             */
+            2 =>
+            {
+                let bus                        : u8 = unsafe { header.read_unaligned(offset + 2) };
+                let irq                         : u8 = unsafe { header.read_unaligned(offset + 3) };
+                let global_system_interrupt     : u32 = unsafe { header.read_unaligned(offset + 4) };
+                let flags                       : u16 = unsafe { header.read_unaligned(offset + 8) };
+
+                model.interrupt_source_overrides.push(InterruptSourceOverride { bus, irq, global_system_interrupt, flags });
+            },
+
+            /*
+             * Local APIC Address Override: a 16-bit reserved field, then a 64-bit address that
+             * supersedes the 32-bit one in the MADT's own header.
+             */
+            5 =>
+            {
+                model.local_apic_address = unsafe { header.read_unaligned(offset + 4) };
+            },
+
+            _ => (),
+        }
+
+        offset += entry_length as usize;
+    }
+
+    Ok(model)
+}
+
+/// Construct the bytes of a MADT with one of each entry type this crate understands, for use in
+/// tests.
+#[cfg(test)]
+pub(crate) fn make_testcase(oem_id : [u8; 6]) -> ::std::boxed::Box<[u8]>
+{
+    let mut body = ::std::vec::Vec::new();
+    body.extend_from_slice(&0xfee0_0000u32.to_ne_bytes());  // local_apic_address
+    body.extend_from_slice(&0u32.to_ne_bytes());            // flags
+
+    // Processor Local APIC: processor id 0, APIC id 0, enabled
+    body.extend_from_slice(&[0, 8, 0, 0]);
+    body.extend_from_slice(&1u32.to_ne_bytes());
+
+    // IO APIC: id 1, reserved, address, global system interrupt base 0
+    body.extend_from_slice(&[1, 12, 1, 0]);
+    body.extend_from_slice(&0xfec0_0000u32.to_ne_bytes());
+    body.extend_from_slice(&0u32.to_ne_bytes());
+
+    // Interrupt Source Override: bus 0, source IRQ 0, global system interrupt 2, flags 0
+    body.extend_from_slice(&[2, 10, 0, 0]);
+    body.extend_from_slice(&2u32.to_ne_bytes());
+    body.extend_from_slice(&0u16.to_ne_bytes());
+
+    SdtHeader::make_testcase_with_body(*b"APIC", 1, oem_id, *b"RUSTTABL", 0, 0, 0, &body)
+}
+
+/// Tests `parse_madt` against deliberately-malformed MADTs.
+#[cfg(test)]
+mod parse_tests
+{
+    use AcpiError;
+    use sdt::SdtHeader;
+    use super::parse_madt;
+
+    const OEM_ID : &[u8; 6] = b"RUST  ";
+
+    /// Reinterpret freshly-constructed testcase bytes as an `SdtHeader`, the same way a real
+    /// `AcpiHandler::map_physical_region::<SdtHeader>` mapping would be read through.
+    unsafe fn as_header(bytes : &[u8]) -> &SdtHeader
+    {
+        &*(bytes.as_ptr() as *const SdtHeader)
+    }
+
+    #[test]
+    fn test_parse_madt_rejects_truncated_header()
+    {
+        let bytes = SdtHeader::make_testcase_with_body(*b"APIC", 1, *OEM_ID, *b"RUSTTABL", 0, 0, 0, &[]);
+        let result = unsafe { parse_madt(as_header(&bytes)) };
+        assert_eq!(result.err(), Some(AcpiError::MadtInvalidLength));
+    }
+
+    #[test]
+    fn test_parse_madt_rejects_zero_length_entry()
+    {
+        let mut body = ::std::vec::Vec::new();
+        body.extend_from_slice(&0xfee0_0000u32.to_ne_bytes());  // local_apic_address
+        body.extend_from_slice(&0u32.to_ne_bytes());            // flags
+        body.extend_from_slice(&[0, 0, 0, 0]);                  // entry type 0, length 0 - would never advance
+
+        let bytes = SdtHeader::make_testcase_with_body(*b"APIC", 1, *OEM_ID, *b"RUSTTABL", 0, 0, 0, &body);
+        let result = unsafe { parse_madt(as_header(&bytes)) };
+        assert_eq!(result.err(), Some(AcpiError::MadtInvalidEntry));
+    }
+
+    #[test]
+    fn test_parse_madt_rejects_entry_overrunning_table()
+    {
+        let mut body = ::std::vec::Vec::new();
+        body.extend_from_slice(&0xfee0_0000u32.to_ne_bytes());  // local_apic_address
+        body.extend_from_slice(&0u32.to_ne_bytes());            // flags
+        body.extend_from_slice(&[1, 12]);                       // entry type 1 (IO APIC), length 12
+        body.extend_from_slice(&[0, 0]);                        // but only 2 of its 10 remaining bytes are present
+
+        let bytes = SdtHeader::make_testcase_with_body(*b"APIC", 1, *OEM_ID, *b"RUSTTABL", 0, 0, 0, &body);
+        let result = unsafe { parse_madt(as_header(&bytes)) };
+        assert_eq!(result.err(), Some(AcpiError::MadtInvalidEntry));
+    }
+
+    #[test]
+    fn test_parse_madt_rejects_entry_shorter_than_its_type_needs()
+    {
+        let mut body = ::std::vec::Vec::new();
+        body.extend_from_slice(&0xfee0_0000u32.to_ne_bytes());  // local_apic_address
+        body.extend_from_slice(&0u32.to_ne_bytes());            // flags
+        body.extend_from_slice(&[1, 2]);                        // entry type 1 (IO APIC), but length is only 2
+
+        let bytes = SdtHeader::make_testcase_with_body(*b"APIC", 1, *OEM_ID, *b"RUSTTABL", 0, 0, 0, &body);
+        let result = unsafe { parse_madt(as_header(&bytes)) };
+        assert_eq!(result.err(), Some(AcpiError::MadtInvalidEntry));
+    }
+}
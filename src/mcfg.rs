@@ -0,0 +1,67 @@
+use alloc::vec::Vec;
+use core::mem;
+use sdt::SdtHeader;
+
+/// Offset, in bytes from the start of the MCFG, of the first allocation entry: the SDT header,
+/// followed by 8 reserved bytes.
+const FIRST_ENTRY_OFFSET : usize = mem::size_of::<SdtHeader>() + 8;
+
+/// Size, in bytes, of a single allocation entry in the MCFG's array.
+const ENTRY_LENGTH : usize = 16;
+
+/// Describes one PCIe Enhanced Configuration Access Mechanism (ECAM) region: the memory-mapped
+/// configuration space for every device on every bus in `bus_start..=bus_end`, on PCI segment
+/// group `segment_group`.
+#[derive(Debug, Clone, Copy)]
+pub struct PciConfigRegion
+{
+    pub base_address    : u64,
+    pub segment_group   : u16,
+    pub bus_start       : u8,
+    pub bus_end         : u8,
+}
+
+impl PciConfigRegion
+{
+    /// The physical address of the configuration space for `(bus, device, function)` within
+    /// this region, per the ECAM layout defined by the PCI Express specification.
+    pub fn address_for(&self, bus : u8, device : u8, function : u8) -> u64
+    {
+        self.base_address + ((u64::from(bus) << 20) | (u64::from(device) << 15) | (u64::from(function) << 12))
+    }
+}
+
+/// Parse an MCFG, given a mapping of its header that extends for the whole table.
+pub fn parse_mcfg(header : &SdtHeader) -> Vec<PciConfigRegion>
+{
+    let mut regions = Vec::new();
+    let mut offset = FIRST_ENTRY_OFFSET;
+
+    while offset + ENTRY_LENGTH <= header.length() as usize
+    {
+        let base_address    : u64 = unsafe { header.read_unaligned(offset) };
+        let segment_group   : u16 = unsafe { header.read_unaligned(offset + 8) };
+        let bus_start       : u8 = unsafe { header.read_unaligned(offset + 10) };
+        let bus_end         : u8 = unsafe { header.read_unaligned(offset + 11) };
+
+        regions.push(PciConfigRegion { base_address, segment_group, bus_start, bus_end });
+
+        offset += ENTRY_LENGTH;
+    }
+
+    regions
+}
+
+/// Construct the bytes of an MCFG with a single allocation entry, for use in tests.
+#[cfg(test)]
+pub(crate) fn make_testcase(oem_id : [u8; 6]) -> ::std::boxed::Box<[u8]>
+{
+    let mut body = ::std::vec::Vec::new();
+    body.extend_from_slice(&[0u8; 8]);                     // reserved
+    body.extend_from_slice(&0xb000_0000u64.to_ne_bytes());  // base_address
+    body.extend_from_slice(&0u16.to_ne_bytes());            // segment_group
+    body.extend_from_slice(&[0, 255]);                      // bus_start, bus_end
+    body.extend_from_slice(&[0u8; 4]);                      // reserved
+
+    SdtHeader::make_testcase_with_body(*b"MCFG", 1, oem_id, *b"RUSTTABL", 0, 0, 0, &body)
+}
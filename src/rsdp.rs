@@ -0,0 +1,325 @@
+use core::mem;
+use core::slice;
+use AcpiError;
+use AcpiHandler;
+
+/// Physical address of the 16-bit real-mode segment pointer to the Extended BIOS Data Area.
+const EBDA_SEGMENT_POINTER : usize = 0x40e;
+
+/// We only need to scan the first KiB of the EBDA; the RSDP is guaranteed to be within it.
+const EBDA_SCAN_LENGTH : usize = 1024;
+
+/// The main BIOS area the RSDP may also live in, on systems with no (or an empty) EBDA.
+const MAIN_BIOS_START : usize = 0xe_0000;
+const MAIN_BIOS_END : usize = 0xf_ffff;
+const MAIN_BIOS_LENGTH : usize = MAIN_BIOS_END - MAIN_BIOS_START + 1;
+
+/// The Root System Description Pointer is the structure that kernels use to bootstrap everything
+/// else in ACPI; it tells us where to find either the RSDT (on ACPI 1.0 systems) or the XSDT (on
+/// ACPI 2.0+ systems).
+///
+/// This always reflects the full ACPI 2.0+ layout. On ACPI 1.0 systems (`revision == 0`), only
+/// the first 20 bytes (up to and including `rsdt_address`) are meaningful; the extended fields
+/// should be treated as unpopulated.
+#[repr(C, packed)]
+pub struct Rsdp
+{
+    signature           : [u8; 8],
+    checksum            : u8,
+    oem_id              : [u8; 6],
+    revision            : u8,
+    rsdt_address        : u32,
+
+    /*
+     * The following fields are only present, and only valid, for ACPI 2.0+ (`revision >= 2`).
+     */
+    length              : u32,
+    xsdt_address        : u64,
+    extended_checksum   : u8,
+    reserved            : [u8; 3],
+}
+
+impl Rsdp
+{
+    /// Validate this RSDP by checking its signature, that the OEM ID is printable ASCII, and that
+    /// the first 20 bytes sum to zero.
+    pub fn validate(&self) -> Result<(), AcpiError>
+    {
+        if &self.signature != b"RSD PTR "
+        {
+            return Err(AcpiError::RsdpIncorrectSignature);
+        }
+
+        if self.oem_id.iter().any(|&byte| !(0x20..=0x7e).contains(&byte))
+        {
+            return Err(AcpiError::RsdpInvalidOemId);
+        }
+
+        if Self::sum_bytes(&self.as_bytes()[0..20]) != 0
+        {
+            return Err(AcpiError::RsdpInvalidChecksum);
+        }
+
+        /*
+         * ACPI 2.0+ RSDPs extend the structure with a length, a 64-bit XSDT address, and a
+         * checksum covering the whole (extended) structure. Validate those too: `length` must
+         * match the structure we actually know how to read, and the extended bytes must sum to
+         * zero.
+         */
+        if self.revision >= 2
+           && (self.length as usize != mem::size_of::<Rsdp>() || Self::sum_bytes(self.as_bytes()) != 0)
+        {
+            return Err(AcpiError::RsdpInvalidExtendedChecksum);
+        }
+
+        Ok(())
+    }
+
+    pub fn revision(&self) -> u8
+    {
+        self.revision
+    }
+
+    pub fn rsdt_address(&self) -> u32
+    {
+        self.rsdt_address
+    }
+
+    /// The 64-bit physical address of the XSDT. Only meaningful when `revision() >= 2`.
+    pub fn xsdt_address(&self) -> u64
+    {
+        self.xsdt_address
+    }
+
+    fn as_bytes(&self) -> &[u8]
+    {
+        unsafe { slice::from_raw_parts(self as *const Rsdp as *const u8, mem::size_of::<Rsdp>()) }
+    }
+
+    fn sum_bytes(bytes: &[u8]) -> u8
+    {
+        bytes.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte))
+    }
+
+    /// Construct an `Rsdp` for use in tests. Checksums are computed automatically unless an
+    /// override is given, which lets tests construct RSDPs with deliberately-invalid checksums.
+    #[cfg(test)]
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn make_testcase(signature                     : [u8; 8],
+                                 checksum_override             : Option<u8>,
+                                 oem_id                        : [u8; 6],
+                                 revision                      : u8,
+                                 rsdt_address                  : u32,
+                                 length                        : u32,
+                                 xsdt_address                  : u64,
+                                 extended_checksum_override    : Option<u8>,
+                                 reserved                      : [u8; 3]) -> ::std::boxed::Box<[u8]>
+    {
+        let mut rsdp = Rsdp
+        {
+            signature,
+            checksum: 0,
+            oem_id,
+            revision,
+            rsdt_address,
+            length,
+            xsdt_address,
+            extended_checksum: 0,
+            reserved,
+        };
+
+        rsdp.checksum = checksum_override.unwrap_or_else(||
+        {
+            0u8.wrapping_sub(Self::sum_bytes(&rsdp.as_bytes()[0..20]))
+        });
+
+        rsdp.extended_checksum = extended_checksum_override.unwrap_or_else(||
+        {
+            0u8.wrapping_sub(Self::sum_bytes(rsdp.as_bytes()))
+        });
+
+        ::std::boxed::Box::from(rsdp.as_bytes())
+    }
+}
+
+/// Find the RSDP by scanning the legacy BIOS memory areas it's conventionally placed in, for
+/// kernels that can't get the physical address handed to them some other way (e.g. by UEFI).
+/// Checks the first KiB of the Extended BIOS Data Area first, then falls back to the main BIOS
+/// area between `0xe_0000` and `0xf_ffff`, in both cases looking on 16-byte boundaries.
+pub fn search_for_rsdp<T>(handler : &T) -> Result<usize, AcpiError>
+    where T : AcpiHandler
+{
+    let ebda_start = ebda_start_address(handler);
+    let ebda_mapping = handler.map_physical_region::<[u8; EBDA_SCAN_LENGTH]>(ebda_start, EBDA_SCAN_LENGTH);
+
+    if let Some(offset) = scan_for_signature(&*ebda_mapping)
+    {
+        return Ok(ebda_start + offset);
+    }
+
+    let bios_mapping = handler.map_physical_region::<[u8; MAIN_BIOS_LENGTH]>(MAIN_BIOS_START, MAIN_BIOS_LENGTH);
+
+    if let Some(offset) = scan_for_signature(&*bios_mapping)
+    {
+        return Ok(MAIN_BIOS_START + offset);
+    }
+
+    Err(AcpiError::RsdpNotFound)
+}
+
+/// Read the real-mode segment pointer at `EBDA_SEGMENT_POINTER` and turn it into a physical
+/// address (a real-mode segment is always `address >> 4`).
+fn ebda_start_address<T>(handler : &T) -> usize
+    where T : AcpiHandler
+{
+    let segment_mapping = handler.map_physical_region::<u16>(EBDA_SEGMENT_POINTER, mem::size_of::<u16>());
+    (*segment_mapping as usize) << 4
+}
+
+/// Scan `region` on 16-byte boundaries for a candidate whose first 8 bytes are the RSDP
+/// signature and whose first 20 bytes sum to zero. Returns the offset of the first match.
+fn scan_for_signature(region : &[u8]) -> Option<usize>
+{
+    for offset in (0..region.len()).step_by(16)
+    {
+        if offset + 20 > region.len()
+        {
+            break;
+        }
+
+        let candidate = &region[offset..offset + 20];
+
+        if &candidate[0..8] == b"RSD PTR " && Rsdp::sum_bytes(candidate) == 0
+        {
+            return Some(offset);
+        }
+    }
+
+    None
+}
+
+/// Tests `search_for_rsdp` against a handler that fabricates the legacy BIOS memory areas
+/// directly, rather than against any particular table (c.f. `constructed_table_tests` in
+/// `lib.rs`, which builds a full chain of ACPI tables).
+#[cfg(test)]
+mod search_tests
+{
+    use std::boxed::Box;
+    use std::ptr::NonNull;
+    use std::slice;
+    use std::vec;
+    use {AcpiError, AcpiHandler, PhysicalMapping};
+    use super::{search_for_rsdp, Rsdp, EBDA_SEGMENT_POINTER, MAIN_BIOS_START};
+
+    /// Fabricates a flat view of physical memory in which the only interesting thing is an RSDP
+    /// placed at `rsdp_offset` bytes into the main BIOS area, if `rsdp_offset` is `Some`; the
+    /// EBDA is always left blank, so the search has to fall through to the main BIOS area to
+    /// find it.
+    #[derive(Clone)]
+    struct BiosHandler { rsdp_offset : Option<usize> }
+
+    impl AcpiHandler for BiosHandler
+    {
+        fn map_physical_region<T>(&self, physical_address : usize, size : usize) -> PhysicalMapping<Self, T>
+        {
+            let mut bytes = vec::from_elem(0u8, size).into_boxed_slice();
+
+            if physical_address == EBDA_SEGMENT_POINTER
+            {
+                bytes[0..2].copy_from_slice(&0u16.to_ne_bytes());
+            }
+            else if physical_address == MAIN_BIOS_START
+            {
+                if let Some(rsdp_offset) = self.rsdp_offset
+                {
+                    let rsdp = Rsdp::make_testcase(*b"RSD PTR ", None, *b"RUST  ", 0, 0, 0, 0, None, [0, 0, 0]);
+                    bytes[rsdp_offset..rsdp_offset + rsdp.len()].copy_from_slice(&rsdp);
+                }
+            }
+
+            PhysicalMapping
+            {
+                physical_start  : physical_address,
+                virtual_start   : unsafe
+                                  {
+                                      NonNull::<T>::new_unchecked(Box::into_raw(bytes) as *mut u8 as *mut T)
+                                  },
+                mapped_length   : size,
+                handler         : self.clone(),
+            }
+        }
+
+        fn unmap_physical_region<T>(&self, region : &PhysicalMapping<Self, T>)
+        {
+            let slice_ptr = unsafe { slice::from_raw_parts_mut(region.virtual_start.as_ptr() as *mut u8, region.mapped_length) };
+            let _ = unsafe { Box::from_raw(slice_ptr) };
+        }
+    }
+
+    #[test]
+    fn test_search_for_rsdp_finds_it_in_main_bios_area()
+    {
+        let handler = BiosHandler { rsdp_offset : Some(32) };
+        let address = search_for_rsdp(&handler).expect("should find the RSDP");
+        assert_eq!(address, MAIN_BIOS_START + 32);
+    }
+
+    #[test]
+    fn test_search_for_rsdp_reports_not_found()
+    {
+        let handler = BiosHandler { rsdp_offset : None };
+        let result = search_for_rsdp(&handler);
+        assert_eq!(result, Err(AcpiError::RsdpNotFound));
+    }
+}
+
+/// Tests `Rsdp::validate` against deliberately-malformed RSDPs, constructed via the
+/// `checksum_override`/`extended_checksum_override` hooks `make_testcase` exists for.
+#[cfg(test)]
+mod validate_tests
+{
+    use AcpiError;
+    use super::Rsdp;
+
+    /// Reinterpret freshly-constructed testcase bytes as an `Rsdp`, the same way a real
+    /// `AcpiHandler::map_physical_region::<Rsdp>` mapping would be read through.
+    unsafe fn as_rsdp(bytes : &[u8]) -> &Rsdp
+    {
+        &*(bytes.as_ptr() as *const Rsdp)
+    }
+
+    #[test]
+    fn test_validate_rejects_wrong_signature()
+    {
+        let bytes = Rsdp::make_testcase(*b"NOPE!!!!", None, *b"RUST  ", 0, 0, 0, 0, None, [0, 0, 0]);
+        assert_eq!(unsafe { as_rsdp(&bytes).validate() }, Err(AcpiError::RsdpIncorrectSignature));
+    }
+
+    #[test]
+    fn test_validate_rejects_non_ascii_oem_id()
+    {
+        let bytes = Rsdp::make_testcase(*b"RSD PTR ", None, [0xff; 6], 0, 0, 0, 0, None, [0, 0, 0]);
+        assert_eq!(unsafe { as_rsdp(&bytes).validate() }, Err(AcpiError::RsdpInvalidOemId));
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_checksum()
+    {
+        let bytes = Rsdp::make_testcase(*b"RSD PTR ", Some(0), *b"RUST  ", 0, 0, 0, 0, None, [0, 0, 0]);
+        assert_eq!(unsafe { as_rsdp(&bytes).validate() }, Err(AcpiError::RsdpInvalidChecksum));
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_extended_checksum()
+    {
+        let bytes = Rsdp::make_testcase(*b"RSD PTR ", None, *b"RUST  ", 2, 0, ::core::mem::size_of::<Rsdp>() as u32, 0, Some(1), [0, 0, 0]);
+        assert_eq!(unsafe { as_rsdp(&bytes).validate() }, Err(AcpiError::RsdpInvalidExtendedChecksum));
+    }
+
+    #[test]
+    fn test_validate_rejects_mismatched_length()
+    {
+        let bytes = Rsdp::make_testcase(*b"RSD PTR ", None, *b"RUST  ", 2, 0, 1, 0, None, [0, 0, 0]);
+        assert_eq!(unsafe { as_rsdp(&bytes).validate() }, Err(AcpiError::RsdpInvalidExtendedChecksum));
+    }
+}
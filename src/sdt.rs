@@ -0,0 +1,250 @@
+use core::mem;
+use core::ptr;
+use core::slice;
+use AcpiError;
+
+/// Every System Description Table other than the RSDP starts with this header, as defined by the
+/// ACPI specification. The table's own fields follow directly after it in memory, and the table's
+/// total length (header included) is given by `length`.
+#[repr(C, packed)]
+pub struct SdtHeader
+{
+    signature           : [u8; 4],
+    length              : u32,
+    revision            : u8,
+    checksum            : u8,
+    oem_id              : [u8; 6],
+    oem_table_id        : [u8; 8],
+    oem_revision        : u32,
+    creator_id          : u32,
+    creator_revision    : u32,
+}
+
+impl SdtHeader
+{
+    pub fn signature(&self) -> [u8; 4]
+    {
+        self.signature
+    }
+
+    pub fn length(&self) -> u32
+    {
+        self.length
+    }
+
+    /// Validate this table: check that its signature is the one expected, that `length` is at
+    /// least big enough to cover the header itself, and that every byte of the table (not just
+    /// the header) sums to zero.
+    ///
+    /// This assumes the mapping this header lives in covers the whole table, not just the
+    /// header, as a mapping made by `map_whole_table` does.
+    pub fn validate(&self, expected_signature: &[u8; 4]) -> Result<(), AcpiError>
+    {
+        if &self.signature != expected_signature
+        {
+            return Err(AcpiError::SdtIncorrectSignature);
+        }
+
+        if (self.length as usize) < mem::size_of::<SdtHeader>()
+        {
+            return Err(AcpiError::SdtInvalidLength);
+        }
+
+        let bytes = unsafe { slice::from_raw_parts(self as *const SdtHeader as *const u8, self.length as usize) };
+
+        if bytes.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte)) != 0
+        {
+            return Err(AcpiError::SdtInvalidChecksum);
+        }
+
+        Ok(())
+    }
+
+    /// Read a `V` at `offset` bytes from the start of this table (the header itself included),
+    /// using an unaligned read, as nothing guarantees a table's fields are aligned for every `V`.
+    /// Used by table-specific parsers to reach past the fields they have a dedicated struct for.
+    ///
+    /// # Safety
+    /// The mapping this header lives in must extend at least `offset + size_of::<V>()` bytes from
+    /// its start, and `offset` must fall within the table's `length`.
+    pub unsafe fn read_unaligned<V>(&self, offset: usize) -> V
+    {
+        let ptr = (self as *const SdtHeader as *const u8).add(offset) as *const V;
+        ptr::read_unaligned(ptr)
+    }
+
+    /// The number of physical pointers of width `P` that follow this header (as used by the
+    /// RSDT's `u32` entries and the XSDT's `u64` entries).
+    fn num_entries<P>(&self) -> usize
+    {
+        (self.length as usize - mem::size_of::<SdtHeader>()) / mem::size_of::<P>()
+    }
+
+    /// Read the `index`th physical pointer of width `P` following this header.
+    ///
+    /// # Safety
+    /// The mapping this header lives in must actually extend for the whole `length` of the table,
+    /// not just the header, and `index` must be in bounds (see [`SdtHeader::num_entries`]).
+    unsafe fn entry<P>(&self, index: usize) -> P
+    {
+        self.read_unaligned(mem::size_of::<SdtHeader>() + index * mem::size_of::<P>())
+    }
+
+    /// The number of 32-bit physical pointers in the RSDT's entry array.
+    pub fn num_entries_u32(&self) -> usize
+    {
+        self.num_entries::<u32>()
+    }
+
+    /// Read the `index`th entry of the RSDT's array of 32-bit physical pointers.
+    ///
+    /// # Safety
+    /// See [`SdtHeader::entry`].
+    pub unsafe fn entry_u32(&self, index: usize) -> u32
+    {
+        self.entry::<u32>(index)
+    }
+
+    /// The number of 64-bit physical pointers in the XSDT's entry array.
+    pub fn num_entries_u64(&self) -> usize
+    {
+        self.num_entries::<u64>()
+    }
+
+    /// Read the `index`th entry of the XSDT's array of 64-bit physical pointers.
+    ///
+    /// # Safety
+    /// See [`SdtHeader::entry`].
+    pub unsafe fn entry_u64(&self, index: usize) -> u64
+    {
+        self.entry::<u64>(index)
+    }
+
+    /// Construct the bytes of a table with this header followed by the given 32-bit entries (as
+    /// in the RSDT), for use in tests. The checksum is computed automatically.
+    #[cfg(test)]
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn make_testcase(signature          : [u8; 4],
+                                 revision           : u8,
+                                 oem_id             : [u8; 6],
+                                 oem_table_id       : [u8; 8],
+                                 oem_revision       : u32,
+                                 creator_id         : u32,
+                                 creator_revision   : u32,
+                                 entries            : &[u32]) -> ::std::boxed::Box<[u8]>
+    {
+        let mut body = ::std::vec::Vec::with_capacity(mem::size_of_val(entries));
+        for &entry in entries
+        {
+            body.extend_from_slice(&entry.to_ne_bytes());
+        }
+
+        Self::make_testcase_with_body(signature, revision, oem_id, oem_table_id, oem_revision, creator_id, creator_revision, &body)
+    }
+
+    /// Construct the bytes of a table with this header followed by the given 64-bit entries (as
+    /// in the XSDT), for use in tests. The checksum is computed automatically.
+    #[cfg(test)]
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn make_testcase_u64(signature         : [u8; 4],
+                                     revision          : u8,
+                                     oem_id            : [u8; 6],
+                                     oem_table_id      : [u8; 8],
+                                     oem_revision      : u32,
+                                     creator_id        : u32,
+                                     creator_revision  : u32,
+                                     entries           : &[u64]) -> ::std::boxed::Box<[u8]>
+    {
+        let mut body = ::std::vec::Vec::with_capacity(mem::size_of_val(entries));
+        for &entry in entries
+        {
+            body.extend_from_slice(&entry.to_ne_bytes());
+        }
+
+        Self::make_testcase_with_body(signature, revision, oem_id, oem_table_id, oem_revision, creator_id, creator_revision, &body)
+    }
+
+    /// Construct the bytes of a table with this header followed by arbitrary body bytes, for use
+    /// in tests. The checksum is computed automatically.
+    #[cfg(test)]
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn make_testcase_with_body(signature         : [u8; 4],
+                                           revision          : u8,
+                                           oem_id            : [u8; 6],
+                                           oem_table_id      : [u8; 8],
+                                           oem_revision      : u32,
+                                           creator_id        : u32,
+                                           creator_revision  : u32,
+                                           body              : &[u8]) -> ::std::boxed::Box<[u8]>
+    {
+        let length = (mem::size_of::<SdtHeader>() + body.len()) as u32;
+
+        let header = SdtHeader
+        {
+            signature,
+            length,
+            revision,
+            checksum: 0,
+            oem_id,
+            oem_table_id,
+            oem_revision,
+            creator_id,
+            creator_revision,
+        };
+
+        let mut bytes = ::std::vec![0u8; length as usize].into_boxed_slice();
+
+        unsafe
+        {
+            ptr::copy_nonoverlapping(&header as *const SdtHeader as *const u8, bytes.as_mut_ptr(), mem::size_of::<SdtHeader>());
+        }
+        bytes[mem::size_of::<SdtHeader>()..].copy_from_slice(body);
+
+        let checksum_offset = 9; // signature(4) + length(4) + revision(1)
+        let sum = bytes.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte));
+        bytes[checksum_offset] = 0u8.wrapping_sub(sum);
+
+        bytes
+    }
+}
+
+/// Tests `SdtHeader::validate` against deliberately-malformed tables.
+#[cfg(test)]
+mod validate_tests
+{
+    use AcpiError;
+    use super::SdtHeader;
+
+    /// Reinterpret freshly-constructed testcase bytes as an `SdtHeader`, the same way a real
+    /// `AcpiHandler::map_physical_region::<SdtHeader>` mapping would be read through.
+    unsafe fn as_header(bytes : &[u8]) -> &SdtHeader
+    {
+        &*(bytes.as_ptr() as *const SdtHeader)
+    }
+
+    #[test]
+    fn test_validate_rejects_wrong_signature()
+    {
+        let bytes = SdtHeader::make_testcase(*b"FOOO", 1, *b"RUST  ", *b"RUSTTABL", 0, 0, 0, &[]);
+        let result = unsafe { as_header(&bytes).validate(b"APIC") };
+        assert_eq!(result, Err(AcpiError::SdtIncorrectSignature));
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_checksum()
+    {
+        let mut bytes = SdtHeader::make_testcase(*b"APIC", 1, *b"RUST  ", *b"RUSTTABL", 0, 0, 0, &[]);
+        bytes[9] = bytes[9].wrapping_add(1);
+        let result = unsafe { as_header(&bytes).validate(b"APIC") };
+        assert_eq!(result, Err(AcpiError::SdtInvalidChecksum));
+    }
+
+    #[test]
+    fn test_validate_rejects_length_shorter_than_header()
+    {
+        let mut bytes = SdtHeader::make_testcase(*b"APIC", 1, *b"RUST  ", *b"RUSTTABL", 0, 0, 0, &[]);
+        bytes[4..8].copy_from_slice(&4u32.to_ne_bytes());
+        let result = unsafe { as_header(&bytes).validate(b"APIC") };
+        assert_eq!(result, Err(AcpiError::SdtInvalidLength));
+    }
+}